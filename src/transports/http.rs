@@ -3,17 +3,19 @@
 use std::fmt;
 use std::ops::Deref;
 use std::sync::atomic::{self, AtomicUsize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::pin::Pin;
+use std::time::Duration;
 
 use crate::error;
 use crate::helpers;
 use crate::rpc;
 use crate::{BatchTransport, Error, RequestId, Transport};
 use futures::task::{Context, Poll};
-use futures::{self, Future, Stream};
-use hyper::header::HeaderValue;
+use futures::{self, Future, FutureExt, Stream};
+use hyper::header::{HeaderMap, HeaderValue};
 use serde_json;
+use tokio::time::Delay;
 use url::Url;
 
 impl From<hyper::Error> for Error {
@@ -47,85 +49,412 @@ impl From<native_tls::Error> for Error {
     }
 }
 
+#[cfg(all(feature = "tls", feature = "rustls-tls"))]
+compile_error!("features `tls` and `rustls-tls` are mutually exclusive, pick one TLS backend");
+
 // The max string length of a request without transfer-encoding: chunked.
 const MAX_SINGLE_CHUNK: usize = 256;
 
-/// HTTP Transport (synchronous)
+/// Connector used by [`Http`] when none is supplied explicitly, picked by the `tls` / `rustls-tls` feature flags.
+#[cfg(feature = "tls")]
+pub type DefaultConnector = hyper_tls::HttpsConnector<hyper::client::HttpConnector>;
+#[cfg(feature = "rustls-tls")]
+pub type DefaultConnector = hyper_rustls::HttpsConnector<hyper::client::HttpConnector>;
+#[cfg(not(any(feature = "tls", feature = "rustls-tls")))]
+pub type DefaultConnector = hyper::client::HttpConnector;
+
+type HttpClient = hyper::Client<DefaultConnector>;
+
+/// The bound `hyper::Client<C>` itself requires to be usable as a request-issuing client.
+pub trait Connector: hyper::client::connect::Connect + Clone + Send + Sync + 'static {}
+impl<C> Connector for C where C: hyper::client::connect::Connect + Clone + Send + Sync + 'static {}
+
+/// HTTP Transport (synchronous), generic over the connector backing its `hyper::Client`.
 #[derive(Debug, Clone)]
-pub struct Http {
+pub struct Http<C = DefaultConnector> {
     id: Arc<AtomicUsize>,
     url: hyper::Uri,
     basic_auth: Option<HeaderValue>,
-    #[cfg(feature = "tls")]
-    client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
-    #[cfg(not(feature = "tls"))]
-    client: hyper::Client<hyper::client::HttpConnector>,
+    headers: HeaderMap,
+    user_agent: HeaderValue,
+    timeout: Option<Duration>,
+    retry: Option<RetryConfig>,
+    client: hyper::Client<C>,
 }
 
-impl Http {
-    /// Create new HTTP transport connecting to given URL.
-    pub fn new(url: &str) -> error::Result<Self> {
-        #[cfg(feature = "tls")]
-        let client = hyper::Client::builder().build::<_, hyper::Body>(hyper_tls::HttpsConnector::new());
-        #[cfg(not(feature = "tls"))]
-        let client = hyper::Client::new();
-
-        let basic_auth = {
-            let url = Url::parse(url)?;
-            let user = url.username();
-            let auth = format!("{}:{}", user, url.password().unwrap_or_default());
-            if &auth == ":" {
-                None
-            } else {
-                Some(HeaderValue::from_str(&format!("Basic {}", base64::encode(&auth)))?)
+const DEFAULT_USER_AGENT: &str = "web3.rs";
+
+/// Configures automatic retries with exponential backoff for transient RPC failures.
+///
+/// Connection resets, connect timeouts, HTTP 429 and 5xx responses are treated as retriable;
+/// any other 4xx response is treated as permanent. The whole-request deadline set with
+/// [`HttpBuilder::timeout`] is not retried: when it passes, the request fails with
+/// `"request timed out"` whatever attempts remain.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Maximum number of attempts (including the first) before giving up.
+    pub max_attempts: usize,
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+    /// Multiplier applied to the delay after each subsequent retry.
+    pub multiplier: f64,
+    /// Upper bound on the backoff delay.
+    pub max_delay: Duration,
+    /// Randomize each delay by up to 50% to avoid many clients retrying in lock-step.
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            jitter: false,
+        }
+    }
+}
+
+impl RetryConfig {
+    fn delay_for(&self, attempt: usize) -> Duration {
+        let scaled = self.base_delay.mul_f64(self.multiplier.powi(attempt as i32));
+        let delay = if self.jitter { jitter(scaled) } else { scaled };
+        std::cmp::min(delay, self.max_delay)
+    }
+}
+
+// Scales `delay` by a pseudo-random factor in [0.5, 1.5), without pulling in a `rand` dependency.
+fn jitter(delay: Duration) -> Duration {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let factor = 0.5 + (nanos % 1_000) as f64 / 1_000.0;
+    delay.mul_f64(factor)
+}
+
+/// Classifies the outcome of a single attempt so the retry layer can decide what to do next.
+enum RetryLogic {
+    Retry(String),
+    DontRetry(String),
+    Successful,
+}
+
+impl RetryLogic {
+    fn for_status(status: hyper::StatusCode) -> Self {
+        if status.is_success() {
+            RetryLogic::Successful
+        } else if status == hyper::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            RetryLogic::Retry(format!("retriable status code: {}", status))
+        } else {
+            RetryLogic::DontRetry(format!("Unexpected response status code: {}", status))
+        }
+    }
+
+    fn for_hyper_error(err: &hyper::Error) -> Self {
+        // A connect timeout surfaces as a connect error and is retried here. The whole-request
+        // deadline is enforced by `Response`'s own timer, not by hyper, and is not retried;
+        // `hyper::Error` has no `is_timeout()` variant to check here.
+        if err.is_connect() || err.is_incomplete_message() || err.is_closed() {
+            RetryLogic::Retry(format!("retriable transport error: {}", err))
+        } else {
+            RetryLogic::DontRetry(format!("{:?}", err))
+        }
+    }
+}
+
+// Everything `Response` needs to rebuild and re-issue a request after a retriable failure.
+// The serialized body is kept around because it is consumed when building the `hyper::Request`.
+struct RetryState<C> {
+    config: RetryConfig,
+    attempt: usize,
+    client: hyper::Client<C>,
+    url: hyper::Uri,
+    basic_auth: Option<HeaderValue>,
+    headers: HeaderMap,
+    user_agent: HeaderValue,
+    body: String,
+}
+
+impl<C> RetryState<C> {
+    // Returns a backoff `Delay` if `logic` is retriable and attempts remain, `None` otherwise.
+    fn backoff(&mut self, logic: &RetryLogic) -> Option<Delay> {
+        match logic {
+            RetryLogic::Retry(_) if self.attempt + 1 < self.config.max_attempts => {
+                let delay = self.config.delay_for(self.attempt);
+                self.attempt += 1;
+                Some(tokio::time::delay_for(delay))
             }
-        };
+            _ => None,
+        }
+    }
+}
+
+impl<C> RetryState<C>
+where
+    C: Connector,
+{
+    fn send(&self) -> hyper::client::ResponseFuture {
+        let req = build_request(
+            &self.url,
+            self.basic_auth.as_ref(),
+            &self.headers,
+            &self.user_agent,
+            &self.body,
+        );
+        self.client.request(req)
+    }
+}
+
+/// Builder for configuring an [`Http`] transport beyond its defaults.
+#[derive(Debug, Clone, Default)]
+pub struct HttpBuilder {
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry: Option<RetryConfig>,
+    headers: HeaderMap,
+    user_agent: Option<HeaderValue>,
+}
+
+impl HttpBuilder {
+    /// Creates a new, unconfigured `HttpBuilder`.
+    pub fn new() -> Self {
+        HttpBuilder::default()
+    }
+
+    /// Sets a deadline for the whole request (connect, send and read the response).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a deadline for establishing the underlying TCP connection. A TLS handshake that
+    /// follows the TCP connect is not covered by it.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Retries transient failures (connection resets, connect timeouts, HTTP 429/5xx) with
+    /// exponential backoff, up to `config.max_attempts`. Expiry of the [`HttpBuilder::timeout`]
+    /// deadline is not retried.
+    pub fn retry(mut self, config: RetryConfig) -> Self {
+        self.retry = Some(config);
+        self
+    }
+
+    /// Merges `headers` into every request.
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Overrides the default `User-Agent: web3.rs` sent with every request.
+    pub fn user_agent(mut self, user_agent: HeaderValue) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Builds the `Http` transport connecting to the given URL.
+    pub fn build(self, url: &str) -> error::Result<Http<DefaultConnector>> {
+        let mut connector = hyper::client::HttpConnector::new();
+        connector.set_connect_timeout(self.connect_timeout);
 
+        self.build_with_client(url, build_client(connector)?)
+    }
+
+    /// Like [`HttpBuilder::build`], but uses a pre-built `hyper::Client<C>` instead of one
+    /// constructed from `connect_timeout`.
+    pub fn build_with_client<C>(self, url: &str, client: hyper::Client<C>) -> error::Result<Http<C>>
+    where
+        C: Connector,
+    {
         Ok(Http {
             id: Arc::new(AtomicUsize::new(1)),
             url: url.parse()?,
-            basic_auth,
+            basic_auth: parse_basic_auth(url)?,
+            headers: self.headers,
+            user_agent: self.user_agent.unwrap_or_else(|| HeaderValue::from_static(DEFAULT_USER_AGENT)),
+            timeout: self.timeout,
+            retry: self.retry,
             client,
         })
     }
 
-    fn send_request<F, O>(&self, id: RequestId, request: rpc::Request, extract: F) -> Response<F>
+    /// Like [`HttpBuilder::build_with_client`], but builds the `hyper::Client` from a bare connector.
+    pub fn build_with_connector<C>(self, url: &str, connector: C) -> error::Result<Http<C>>
     where
-        F: Fn(Vec<u8>) -> O,
+        C: Connector,
     {
-        let request = helpers::to_string(&request);
-        log::debug!("[{}] Sending: {} to {}", id, request, self.url);
-        let len = request.len();
-        let mut req = hyper::Request::new(hyper::Body::from(request));
-        *req.method_mut() = hyper::Method::POST;
-        *req.uri_mut() = self.url.clone();
-        req.headers_mut().insert(
-            hyper::header::CONTENT_TYPE,
-            HeaderValue::from_static("application/json"),
-        );
-        req.headers_mut()
-            .insert(hyper::header::USER_AGENT, HeaderValue::from_static("web3.rs"));
+        self.build_with_client(url, hyper::Client::builder().build(connector))
+    }
+}
 
-        // Don't send chunked request
-        if len < MAX_SINGLE_CHUNK {
-            req.headers_mut().insert(hyper::header::CONTENT_LENGTH, len.into());
-        }
+// Wraps `connector` in the TLS layer selected at compile time (`tls` or `rustls-tls`), or
+// leaves it bare when neither feature is enabled.
+#[cfg(feature = "tls")]
+fn build_client(mut connector: hyper::client::HttpConnector) -> error::Result<HttpClient> {
+    connector.enforce_http(false);
+
+    let https = hyper_tls::HttpsConnector::from((connector, native_tls::TlsConnector::new()?.into()));
+    Ok(hyper::Client::builder().build::<_, hyper::Body>(https))
+}
+
+#[cfg(feature = "rustls-tls")]
+fn build_client(mut connector: hyper::client::HttpConnector) -> error::Result<HttpClient> {
+    connector.enforce_http(false);
+
+    let mut tls_config = rustls::ClientConfig::new();
+    tls_config.root_store =
+        rustls_native_certs::load_native_certs().map_err(|(_, err)| Error::Transport(format!("{:?}", err)))?;
+
+    let https = hyper_rustls::HttpsConnector::from((connector, tls_config));
+    Ok(hyper::Client::builder().build::<_, hyper::Body>(https))
+}
+
+#[cfg(not(any(feature = "tls", feature = "rustls-tls")))]
+fn build_client(connector: hyper::client::HttpConnector) -> error::Result<HttpClient> {
+    Ok(hyper::Client::builder().build::<_, hyper::Body>(connector))
+}
+
+// Builds a POST request for `body`, used both for the initial send and for each retry attempt.
+fn build_request(
+    url: &hyper::Uri,
+    basic_auth: Option<&HeaderValue>,
+    headers: &HeaderMap,
+    user_agent: &HeaderValue,
+    body: &str,
+) -> hyper::Request<hyper::Body> {
+    let len = body.len();
+    let mut req = hyper::Request::new(hyper::Body::from(body.to_owned()));
+    *req.method_mut() = hyper::Method::POST;
+    *req.uri_mut() = url.clone();
+    req.headers_mut()
+        .insert(hyper::header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    req.headers_mut().insert(hyper::header::USER_AGENT, user_agent.clone());
 
-        // Send basic auth header
-        if let Some(ref basic_auth) = self.basic_auth {
-            req.headers_mut()
-                .insert(hyper::header::AUTHORIZATION, basic_auth.clone());
+    // Don't send chunked request
+    if len < MAX_SINGLE_CHUNK {
+        req.headers_mut().insert(hyper::header::CONTENT_LENGTH, len.into());
+    }
+
+    // Send basic auth header
+    if let Some(basic_auth) = basic_auth {
+        req.headers_mut().insert(hyper::header::AUTHORIZATION, basic_auth.clone());
+    }
+
+    // Extra headers take precedence over the defaults above (e.g. a caller-supplied
+    // Authorization header for an API key instead of basic auth). `HeaderMap`'s iterator
+    // yields one pair per stored value, so only the first occurrence of a name may `insert`
+    // (overwriting any default); later occurrences must `append` or a caller-supplied header
+    // with multiple values (e.g. repeated `Cookie`) would lose all but the last on the wire.
+    let mut seen = std::collections::HashSet::new();
+    for (name, value) in headers {
+        if seen.insert(name.clone()) {
+            req.headers_mut().insert(name.clone(), value.clone());
+        } else {
+            req.headers_mut().append(name.clone(), value.clone());
         }
-        let result = self
-            .client
-            .request(req);
+    }
 
-        Response::new(id, result, extract)
+    req
+}
+
+fn parse_basic_auth(url: &str) -> error::Result<Option<HeaderValue>> {
+    let url = Url::parse(url)?;
+    let user = url.username();
+    let auth = format!("{}:{}", user, url.password().unwrap_or_default());
+    if &auth == ":" {
+        Ok(None)
+    } else {
+        Ok(Some(HeaderValue::from_str(&format!("Basic {}", base64::encode(&auth)))?))
     }
 }
 
-impl Transport for Http {
-    type Out = Response<fn(Vec<u8>) -> error::Result<rpc::Value>>;
+impl Http<DefaultConnector> {
+    /// Create new HTTP transport connecting to given URL.
+    pub fn new(url: &str) -> error::Result<Self> {
+        Ok(Http {
+            id: Arc::new(AtomicUsize::new(1)),
+            url: url.parse()?,
+            basic_auth: parse_basic_auth(url)?,
+            headers: HeaderMap::new(),
+            user_agent: HeaderValue::from_static(DEFAULT_USER_AGENT),
+            timeout: None,
+            retry: None,
+            client: build_client(hyper::client::HttpConnector::new())?,
+        })
+    }
+
+    /// Create new HTTP transport connecting to given URL, bounding the whole request
+    /// (connect, send and read the response) to `timeout`.
+    pub fn with_timeout(url: &str, timeout: Duration) -> error::Result<Self> {
+        HttpBuilder::new().timeout(timeout).build(url)
+    }
+
+    /// Create new HTTP transport connecting to given URL, automatically retrying
+    /// transient failures according to `retry`. Applies to both single and batch sends.
+    pub fn with_retry(url: &str, retry: RetryConfig) -> error::Result<Self> {
+        HttpBuilder::new().retry(retry).build(url)
+    }
+
+    /// Create new HTTP transport connecting to given URL, merging `headers` into every request.
+    pub fn with_headers(url: &str, headers: HeaderMap) -> error::Result<Self> {
+        HttpBuilder::new().headers(headers).build(url)
+    }
+}
+
+impl<C> Http<C>
+where
+    C: Connector,
+{
+    /// Create new HTTP transport connecting to given URL, using a pre-built `hyper::Client`.
+    pub fn with_client(url: &str, client: hyper::Client<C>) -> error::Result<Self> {
+        Ok(Http {
+            id: Arc::new(AtomicUsize::new(1)),
+            url: url.parse()?,
+            basic_auth: parse_basic_auth(url)?,
+            headers: HeaderMap::new(),
+            user_agent: HeaderValue::from_static(DEFAULT_USER_AGENT),
+            timeout: None,
+            retry: None,
+            client,
+        })
+    }
+
+    /// Like [`Http::with_client`], but builds the `hyper::Client` from a bare connector.
+    pub fn with_connector(url: &str, connector: C) -> error::Result<Self> {
+        Self::with_client(url, hyper::Client::builder().build(connector))
+    }
+
+    fn send_request<F, O>(&self, id: RequestId, request: rpc::Request, extract: F) -> Response<C, F>
+    where
+        F: Fn(Vec<u8>) -> O,
+    {
+        let body = helpers::to_string(&request);
+        log::debug!("[{}] Sending: {} to {}", id, body, self.url);
+        let req = build_request(&self.url, self.basic_auth.as_ref(), &self.headers, &self.user_agent, &body);
+        let result = self.client.request(req);
+
+        let retry = self.retry.clone().map(|config| RetryState {
+            config,
+            attempt: 0,
+            client: self.client.clone(),
+            url: self.url.clone(),
+            basic_auth: self.basic_auth.clone(),
+            headers: self.headers.clone(),
+            user_agent: self.user_agent.clone(),
+            body,
+        });
+
+        Response::new(id, result, extract, self.timeout, retry)
+    }
+}
+
+impl<C> Transport for Http<C>
+where
+    C: Connector,
+{
+    type Out = Response<C, fn(Vec<u8>) -> error::Result<rpc::Value>>;
 
     fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
         let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
@@ -139,8 +468,11 @@ impl Transport for Http {
     }
 }
 
-impl BatchTransport for Http {
-    type Batch = Response<fn(Vec<u8>) -> error::Result<Vec<error::Result<rpc::Value>>>>;
+impl<C> BatchTransport for Http<C>
+where
+    C: Connector,
+{
+    type Batch = Response<C, fn(Vec<u8>) -> error::Result<Vec<error::Result<rpc::Value>>>>;
 
     fn send_batch<T>(&self, requests: T) -> Self::Batch
     where
@@ -176,30 +508,43 @@ fn batch_response<T: Deref<Target = [u8]>>(response: T) -> error::Result<Vec<err
 
 enum ResponseState {
     Waiting(hyper::client::ResponseFuture),
+    Draining(hyper::Body, Option<Delay>),
+    Backoff(Delay),
     Reading(Vec<u8>, hyper::Body),
 }
 
 /// A future representing a response to a pending request.
-pub struct Response<T> {
+pub struct Response<C, T> {
     id: RequestId,
     extract: T,
     state: ResponseState,
+    deadline: Option<Delay>,
+    retry: Option<RetryState<C>>,
 }
 
-impl<T> Response<T> {
+impl<C, T> Response<C, T> {
     /// Creates a new `Response`
-    pub fn new(id: RequestId, response: hyper::client::ResponseFuture, extract: T) -> Self {
+    pub fn new(
+        id: RequestId,
+        response: hyper::client::ResponseFuture,
+        extract: T,
+        timeout: Option<Duration>,
+        retry: Option<RetryState<C>>,
+    ) -> Self {
         log::trace!("[{}] Request pending.", id);
         Response {
             id,
             extract,
-            state: ResponseState::Waiting(response)
+            state: ResponseState::Waiting(response),
+            deadline: timeout.map(|timeout| tokio::time::delay_for(timeout)),
+            retry,
         }
     }
 }
 
-impl<T, Out> Future for Response<T>
+impl<C, T, Out> Future for Response<C, T>
 where
+    C: Connector,
     T: Fn(Vec<u8>) -> error::Result<Out> + Unpin,
     Out: fmt::Debug + Unpin,
 {
@@ -207,18 +552,62 @@ where
 
     fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
         let id = self.id;
+        if let Some(ref mut deadline) = self.deadline {
+            if Pin::new(deadline).poll(ctx).is_ready() {
+                log::trace!("[{}] Request timed out.", id);
+                return Poll::Ready(Err(Error::Transport("request timed out".into())));
+            }
+        }
         loop {
             match self.state {
                 ResponseState::Waiting(ref mut waiting) => {
                     log::trace!("[{}] Checking response.", id);
-                    let response = ready!(Pin::new(waiting).poll(ctx))?;
-                    if !response.status().is_success() {
-                        return Poll::Ready(Err(Error::Transport(format!(
-                                        "Unexpected response status code: {}",
-                                        response.status()
-                        ))));
+                    let response = match ready!(Pin::new(waiting).poll(ctx)) {
+                        Ok(response) => response,
+                        Err(err) => {
+                            let logic = RetryLogic::for_hyper_error(&err);
+                            match self.retry.as_mut().and_then(|retry| retry.backoff(&logic)) {
+                                Some(delay) => {
+                                    self.state = ResponseState::Backoff(delay);
+                                    continue;
+                                }
+                                None => return Poll::Ready(Err(err.into())),
+                            }
+                        }
+                    };
+                    match RetryLogic::for_status(response.status()) {
+                        RetryLogic::Successful => {
+                            self.state = ResponseState::Reading(Default::default(), response.into_body());
+                        }
+                        logic => match self.retry.as_mut().and_then(|retry| retry.backoff(&logic)) {
+                            Some(delay) => {
+                                // Drain the body so hyper can return the connection to its pool
+                                // instead of closing it, the same as the `Reading` arm does for
+                                // successful responses.
+                                self.state = ResponseState::Draining(response.into_body(), Some(delay));
+                            }
+                            None => {
+                                return Poll::Ready(Err(Error::Transport(format!(
+                                    "Unexpected response status code: {}",
+                                    response.status()
+                                ))));
+                            }
+                        },
                     }
-                    self.state = ResponseState::Reading(Default::default(), response.into_body());
+                },
+                ResponseState::Draining(ref mut body, ref mut delay) => {
+                    log::trace!("[{}] Draining body before retry.", id);
+                    if ready!(Pin::new(body).poll_next(ctx)).transpose()?.is_some() {
+                        continue;
+                    }
+                    let delay = delay.take().expect("Draining state always holds a delay");
+                    self.state = ResponseState::Backoff(delay);
+                },
+                ResponseState::Backoff(ref mut delay) => {
+                    ready!(Pin::new(delay).poll(ctx));
+                    let retry = self.retry.as_ref().expect("Backoff state only reached with retry configured");
+                    log::debug!("[{}] Retrying request (attempt {}).", id, retry.attempt + 1);
+                    self.state = ResponseState::Waiting(retry.send());
                 },
                 ResponseState::Reading(ref mut content, ref mut body) => {
                     log::trace!("[{}] Reading body.", id);
@@ -242,6 +631,167 @@ where
     }
 }
 
+/// Returns the raw response body unchanged, for use as the `extract` function of a `Response`
+/// whose caller wants the bytes rather than a decoded `rpc::Value`.
+fn raw_bytes(bytes: Vec<u8>) -> error::Result<Vec<u8>> {
+    Ok(bytes)
+}
+
+/// Exposes an [`Http`] transport as a [`tower::Service`], for composing with a `tower::ServiceBuilder`.
+#[derive(Debug, Clone)]
+pub struct HttpService<C = DefaultConnector> {
+    http: Http<C>,
+}
+
+impl<C> HttpService<C> {
+    /// Wraps `http` as a `tower::Service`.
+    pub fn new(http: Http<C>) -> Self {
+        HttpService { http }
+    }
+}
+
+impl<C> tower::Service<(RequestId, rpc::Request)> for HttpService<C>
+where
+    C: Connector,
+{
+    type Response = Vec<u8>;
+    type Error = Error;
+    type Future = Response<C, fn(Vec<u8>) -> error::Result<Vec<u8>>>;
+
+    fn poll_ready(&mut self, _ctx: &mut Context<'_>) -> Poll<error::Result<()>> {
+        // `Http` has no concurrency cap of its own; readiness is governed by whatever
+        // layer (e.g. a concurrency limit) sits in front of this service.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, (id, request): (RequestId, rpc::Request)) -> Self::Future {
+        self.http.send_request(id, request, raw_bytes)
+    }
+}
+
+/// Adapts a [`tower::Service`] that turns a JSON-RPC request into raw response bytes into a
+/// [`Transport`]/[`BatchTransport`]. Polls `poll_ready` to completion before every `call`, so
+/// middleware relying on the `tower::Service` contract (e.g. `ConcurrencyLimit`, `RateLimit`)
+/// works correctly.
+#[derive(Debug)]
+pub struct TowerTransport<S> {
+    id: Arc<AtomicUsize>,
+    service: Arc<Mutex<S>>,
+}
+
+enum TowerCallState {
+    Pending { id: RequestId, request: Option<rpc::Request> },
+    Called(Pin<Box<dyn Future<Output = error::Result<Vec<u8>>> + Send>>),
+}
+
+// Polls `poll_ready` to completion and immediately follows it with `call`, without letting any
+// other `TowerCall` on the same `service` observe readiness in between.
+struct TowerCall<S> {
+    service: Arc<Mutex<S>>,
+    state: TowerCallState,
+}
+
+impl<S> TowerCall<S> {
+    fn new(service: Arc<Mutex<S>>, id: RequestId, request: rpc::Request) -> Self {
+        TowerCall {
+            service,
+            state: TowerCallState::Pending {
+                id,
+                request: Some(request),
+            },
+        }
+    }
+}
+
+impl<S> Future for TowerCall<S>
+where
+    S: tower::Service<(RequestId, rpc::Request), Response = Vec<u8>, Error = Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Output = error::Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            match &mut this.state {
+                TowerCallState::Pending { id, request } => {
+                    let mut service = this.service.lock().expect("tower service poisoned");
+                    match service.poll_ready(ctx) {
+                        Poll::Ready(Ok(())) => {
+                            let id = *id;
+                            let request = request.take().expect("Pending state polled after request was taken");
+                            let future = service.call((id, request));
+                            drop(service);
+                            this.state = TowerCallState::Called(Box::pin(future));
+                        }
+                        Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                TowerCallState::Called(future) => return future.as_mut().poll(ctx),
+            }
+        }
+    }
+}
+
+// Implemented by hand: cloning only bumps the `Arc` refcounts, it doesn't require `S: Clone`.
+impl<S> Clone for TowerTransport<S> {
+    fn clone(&self) -> Self {
+        TowerTransport {
+            id: self.id.clone(),
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<S> TowerTransport<S> {
+    /// Wraps `service` as a `Transport`/`BatchTransport`.
+    pub fn new(service: S) -> Self {
+        TowerTransport {
+            id: Arc::new(AtomicUsize::new(1)),
+            service: Arc::new(Mutex::new(service)),
+        }
+    }
+}
+
+impl<S> Transport for TowerTransport<S>
+where
+    S: tower::Service<(RequestId, rpc::Request), Response = Vec<u8>, Error = Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Out = Pin<Box<dyn Future<Output = error::Result<rpc::Value>> + Send>>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let call = TowerCall::new(self.service.clone(), id, rpc::Request::Single(request));
+        Box::pin(call.map(|result| single_response(result?)))
+    }
+}
+
+impl<S> BatchTransport for TowerTransport<S>
+where
+    S: tower::Service<(RequestId, rpc::Request), Response = Vec<u8>, Error = Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Batch = Pin<Box<dyn Future<Output = error::Result<Vec<error::Result<rpc::Value>>>> + Send>>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let mut it = requests.into_iter();
+        let (id, first) = it.next().map(|x| (x.0, Some(x.1))).unwrap_or_else(|| (0, None));
+        let requests = first.into_iter().chain(it.map(|x| x.1)).collect();
+
+        let call = TowerCall::new(self.service.clone(), id, rpc::Request::Batch(requests));
+        Box::pin(call.map(|result| batch_response(result?)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -262,6 +812,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn delay_for_computes_exponential_backoff_without_jitter() {
+        let config = RetryConfig {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            jitter: false,
+        };
+
+        assert_eq!(config.delay_for(0), Duration::from_millis(100));
+        assert_eq!(config.delay_for(1), Duration::from_millis(200));
+        assert_eq!(config.delay_for(2), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn delay_for_caps_at_max_delay() {
+        let config = RetryConfig {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            multiplier: 10.0,
+            max_delay: Duration::from_millis(500),
+            jitter: false,
+        };
+
+        assert_eq!(config.delay_for(3), Duration::from_millis(500));
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn http_builds_over_https_with_native_tls_backend() {
+        assert!(Http::new("https://127.0.0.1:8545").is_ok());
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    #[test]
+    fn http_builds_over_https_with_rustls_backend() {
+        assert!(Http::new("https://127.0.0.1:8545").is_ok());
+    }
+
     #[test]
     fn http_supports_basic_auth_with_user_no_password() {
         let http = Http::new("https://username:@127.0.0.1:8545");
@@ -336,4 +926,235 @@ mod tests {
         // then
         assert_eq!(response, Ok(rpc::Value::String("x".into())));
     }
+
+    #[tokio::test]
+    async fn with_timeout_errors_when_server_stalls() {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let addr = "127.0.0.1:3005";
+        let service = make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|_req: hyper::Request<hyper::Body>| async {
+                tokio::time::delay_for(Duration::from_secs(10)).await;
+                Ok::<_, hyper::Error>(hyper::Response::new(hyper::Body::from("{}")))
+            }))
+        });
+        let server = hyper::Server::bind(&addr.parse().unwrap()).serve(service);
+        tokio::spawn(async move { server.await.unwrap() });
+
+        let client = Http::with_timeout(&format!("http://{}", addr), Duration::from_millis(20)).unwrap();
+        let response = client.execute("eth_getAccounts", vec![]).await;
+
+        assert_eq!(response, Err(Error::Transport("request timed out".into())));
+    }
+
+    #[tokio::test]
+    async fn extra_headers_and_user_agent_reach_the_wire() {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let addr = "127.0.0.1:3007";
+        let service = make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|req: hyper::Request<hyper::Body>| async move {
+                assert_eq!(req.headers().get("x-api-key").unwrap(), "secret");
+                assert_eq!(req.headers().get(hyper::header::USER_AGENT).unwrap(), "my-agent/1.0");
+                Ok::<_, hyper::Error>(hyper::Response::new(hyper::Body::from(
+                    r#"{"jsonrpc":"2.0","id":1,"result":"x"}"#,
+                )))
+            }))
+        });
+        let server = hyper::Server::bind(&addr.parse().unwrap()).serve(service);
+        tokio::spawn(async move { server.await.unwrap() });
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+
+        let client = HttpBuilder::new()
+            .headers(headers)
+            .user_agent(HeaderValue::from_static("my-agent/1.0"))
+            .build(&format!("http://{}", addr))
+            .unwrap();
+
+        let response = client.execute("eth_getAccounts", vec![]).await;
+        assert_eq!(response, Ok(rpc::Value::String("x".into())));
+    }
+
+    #[test]
+    fn build_request_keeps_all_values_of_a_multi_valued_extra_header() {
+        let mut headers = HeaderMap::new();
+        headers.append("cookie", HeaderValue::from_static("a=1"));
+        headers.append("cookie", HeaderValue::from_static("b=2"));
+
+        let req = build_request(
+            &"http://127.0.0.1:8545".parse().unwrap(),
+            None,
+            &headers,
+            &HeaderValue::from_static("my-agent/1.0"),
+            "{}",
+        );
+
+        let cookies: Vec<_> = req.headers().get_all("cookie").iter().collect();
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+    }
+
+    #[tokio::test]
+    async fn http_with_client_and_with_connector_make_requests() {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let addr = "127.0.0.1:3008";
+        let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(server)) });
+        let server = hyper::Server::bind(&addr.parse().unwrap()).serve(service);
+        tokio::spawn(async move { server.await.unwrap() });
+        let url = format!("http://{}", addr);
+
+        let hyper_client = hyper::Client::builder().build(hyper::client::HttpConnector::new());
+        let via_client = Http::with_client(&url, hyper_client).unwrap();
+        assert_eq!(
+            via_client.execute("eth_getAccounts", vec![]).await,
+            Ok(rpc::Value::String("x".into()))
+        );
+
+        let via_connector = Http::with_connector(&url, hyper::client::HttpConnector::new()).unwrap();
+        assert_eq!(
+            via_connector.execute("eth_getAccounts", vec![]).await,
+            Ok(rpc::Value::String("x".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn builder_build_with_client_and_build_with_connector_make_requests() {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let addr = "127.0.0.1:3009";
+        let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(server)) });
+        let server = hyper::Server::bind(&addr.parse().unwrap()).serve(service);
+        tokio::spawn(async move { server.await.unwrap() });
+        let url = format!("http://{}", addr);
+
+        let hyper_client = hyper::Client::builder().build(hyper::client::HttpConnector::new());
+        let via_client = HttpBuilder::new().build_with_client(&url, hyper_client).unwrap();
+        assert_eq!(
+            via_client.execute("eth_getAccounts", vec![]).await,
+            Ok(rpc::Value::String("x".into()))
+        );
+
+        let via_connector = HttpBuilder::new()
+            .build_with_connector(&url, hyper::client::HttpConnector::new())
+            .unwrap();
+        assert_eq!(
+            via_connector.execute("eth_getAccounts", vec![]).await,
+            Ok(rpc::Value::String("x".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failure_then_succeeds() {
+        use hyper::service::{make_service_fn, service_fn};
+        use std::sync::atomic::Ordering;
+
+        let addr = "127.0.0.1:3002";
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let attempts_for_server = attempts.clone();
+        let service = make_service_fn(move |_| {
+            let attempts = attempts_for_server.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |_req: hyper::Request<hyper::Body>| {
+                    let attempts = attempts.clone();
+                    async move {
+                        let response = if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                            hyper::Response::builder()
+                                .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+                                .body(hyper::Body::empty())
+                                .unwrap()
+                        } else {
+                            hyper::Response::new(r#"{"jsonrpc":"2.0","id":1,"result":"x"}"#.into())
+                        };
+                        Ok::<_, hyper::Error>(response)
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&addr.parse().unwrap()).serve(service);
+        tokio::spawn(async move { server.await.unwrap() });
+
+        let retry = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            multiplier: 1.0,
+            max_delay: Duration::from_millis(5),
+            jitter: false,
+        };
+        let client = Http::with_retry(&format!("http://{}", addr), retry).unwrap();
+
+        let response = client.execute("eth_getAccounts", vec![]).await;
+        assert_eq!(response, Ok(rpc::Value::String("x".into())));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_failure() {
+        use hyper::service::{make_service_fn, service_fn};
+        use std::sync::atomic::Ordering;
+
+        let addr = "127.0.0.1:3003";
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let attempts_for_server = attempts.clone();
+        let service = make_service_fn(move |_| {
+            let attempts = attempts_for_server.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |_req: hyper::Request<hyper::Body>| {
+                    let attempts = attempts.clone();
+                    async move {
+                        attempts.fetch_add(1, Ordering::SeqCst);
+                        Ok::<_, hyper::Error>(
+                            hyper::Response::builder()
+                                .status(hyper::StatusCode::NOT_FOUND)
+                                .body(hyper::Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&addr.parse().unwrap()).serve(service);
+        tokio::spawn(async move { server.await.unwrap() });
+
+        let client = Http::with_retry(&format!("http://{}", addr), RetryConfig::default()).unwrap();
+
+        let response = client.execute("eth_getAccounts", vec![]).await;
+        assert!(response.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    // A service whose `call` panics unless it was immediately preceded by a `Ready` `poll_ready`,
+    // mirroring the permit-in-`poll_ready` contract of `tower::limit::ConcurrencyLimit`/`RateLimit`.
+    struct PermitService {
+        permitted: bool,
+    }
+
+    impl tower::Service<(RequestId, rpc::Request)> for PermitService {
+        type Response = Vec<u8>;
+        type Error = Error;
+        type Future = futures::future::Ready<error::Result<Vec<u8>>>;
+
+        fn poll_ready(&mut self, _ctx: &mut Context<'_>) -> Poll<error::Result<()>> {
+            self.permitted = true;
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: (RequestId, rpc::Request)) -> Self::Future {
+            assert!(self.permitted, "call() without a preceding poll_ready()");
+            self.permitted = false;
+            futures::future::ready(Ok(br#"{"jsonrpc":"2.0","id":1,"result":"x"}"#.to_vec()))
+        }
+    }
+
+    #[tokio::test]
+    async fn tower_transport_polls_ready_before_every_call() {
+        let transport = TowerTransport::new(PermitService { permitted: false });
+
+        for _ in 0..2 {
+            let (id, request) = transport.prepare("eth_getAccounts", vec![]);
+            let response = transport.send(id, request).await;
+            assert_eq!(response, Ok(rpc::Value::String("x".into())));
+        }
+    }
 }